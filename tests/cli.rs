@@ -0,0 +1,199 @@
+use eyre::Result;
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn patterns_paths_and_content_in_one_run() -> Result<()> {
+    let config_dir = tempfile::tempdir()?;
+    fs::write(config_dir.path().join("txt.yml"), "included_paths: [\"*.txt\"]\n")?;
+    let tree = tempfile::tempdir()?;
+    let base = tree.path().canonicalize()?;
+    fs::write(base.join("hello.txt"), "hello world\n")?;
+
+    let kat = |extra: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_kat"))
+            .arg("--config-dir")
+            .arg(config_dir.path())
+            .args(extra)
+            .arg("txt")
+            .arg("--path")
+            .arg(&base)
+            .arg("--no-pager")
+            .env("KAT_VIEWER", "cat")
+            .output()?;
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    let stdout = kat(&["-P", "-p"])?;
+    assert!(stdout.contains("*.txt"), "got: {}", stdout);
+    assert!(stdout.contains("results:"), "got: {}", stdout);
+    assert!(stdout.contains("hello world"), "got: {}", stdout);
+
+    let stdout = kat(&["-P", "-p", "--no-content"])?;
+    assert!(stdout.contains("results:"), "got: {}", stdout);
+    assert!(!stdout.contains("hello world"), "got: {}", stdout);
+    Ok(())
+}
+
+#[test]
+fn exit_codes_distinguish_usage_and_config_errors() -> Result<()> {
+    let output = Command::new(env!("CARGO_BIN_EXE_kat")).arg("--bogus-flag").output()?;
+    assert_eq!(output.status.code(), Some(2));
+
+    let missing = tempfile::tempdir()?.path().join("missing");
+    let output = Command::new(env!("CARGO_BIN_EXE_kat"))
+        .arg("--config-dir")
+        .arg(&missing)
+        .arg("ptns")
+        .arg("-i")
+        .arg("*.rs")
+        .output()?;
+    assert_eq!(
+        output.status.code(),
+        Some(3),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(())
+}
+
+#[test]
+fn count_only_prints_the_match_count() -> Result<()> {
+    let config_dir = tempfile::tempdir()?;
+    fs::write(
+        config_dir.path().join("rust.yml"),
+        "included_paths: [\"Cargo.toml\", \"build.rs\", \"src/**/*.rs\"]\nexcluded_paths: [\"target/**\"]\n",
+    )?;
+    let count = |extra: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_kat"))
+            .arg("--config-dir")
+            .arg(config_dir.path())
+            .arg("rust")
+            .arg("--path")
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/rust"))
+            .arg("--count-only")
+            .args(extra)
+            .output()?;
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    assert_eq!(count(&[])?, "10\n");
+    assert_eq!(count(&["--max-depth", "1"])?, "2\n");
+    assert_eq!(count(&["-x", "src/lib/**"])?, "6\n");
+    Ok(())
+}
+
+#[test]
+fn profile_reports_each_phase() -> Result<()> {
+    let config_dir = tempfile::tempdir()?;
+    fs::write(config_dir.path().join("rust.yml"), "included_paths: [\"**/*.rs\"]\n")?;
+    let run = |extra: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_kat"))
+            .arg("--config-dir")
+            .arg(config_dir.path())
+            .arg("rust")
+            .arg("--path")
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/rust"))
+            .arg("--count-only")
+            .args(extra)
+            .output()?;
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        Ok(String::from_utf8(output.stderr)?)
+    };
+    let stderr = run(&["--profile"])?;
+    for phase in ["config loading", "walk", "glob matching", "content emission"] {
+        assert!(stderr.contains(&format!("profile: {} ", phase)), "got: {}", stderr);
+    }
+    assert!(!run(&[])?.contains("profile:"));
+    Ok(())
+}
+
+#[test]
+fn tree_shows_the_nesting_of_matched_files() -> Result<()> {
+    let config_dir = tempfile::tempdir()?;
+    fs::write(
+        config_dir.path().join("rust.yml"),
+        "included_paths: [\"Cargo.toml\", \"src/**/*.rs\"]\nexcluded_paths: [\"src/lib/feature*.rs\"]\n",
+    )?;
+    let root = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/rust");
+    let output = Command::new(env!("CARGO_BIN_EXE_kat"))
+        .arg("--config-dir")
+        .arg(config_dir.path())
+        .arg("rust")
+        .arg("--path")
+        .arg(root)
+        .arg("--tree")
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!(
+            "{} (7)\n\
+             ├── Cargo.toml\n\
+             └── src/ (6)\n    \
+                 ├── lib/ (2)\n    \
+                 │   ├── config.rs\n    \
+                 │   └── mod.rs\n    \
+                 ├── main.rs\n    \
+                 └── utils/ (3)\n        \
+                     ├── helper1.rs\n        \
+                     ├── helper2.rs\n        \
+                     └── mod.rs\n",
+            fs::canonicalize(root)?.display()
+        )
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn walk_errors_are_reported_on_stderr() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let config_dir = tempfile::tempdir()?;
+    fs::write(config_dir.path().join("txt.yml"), "included_paths: [\"**/*.txt\"]\n")?;
+    let tree = tempfile::tempdir()?;
+    let base = tree.path().canonicalize()?;
+    fs::write(base.join("a.txt"), "")?;
+    fs::create_dir(base.join("locked"))?;
+    fs::write(base.join("locked/b.txt"), "")?;
+    std::os::unix::fs::symlink(base.join("missing.txt"), base.join("dangling.txt"))?;
+
+    let stderr = |extra: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_kat"))
+            .arg("--config-dir")
+            .arg(config_dir.path())
+            .arg("--no-content")
+            .arg("txt")
+            .arg("--path")
+            .arg(&base)
+            .args(extra)
+            .output()?;
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        Ok(String::from_utf8(output.stderr)?)
+    };
+
+    // A followed symlink to nowhere fails even for root
+    let warned = stderr(&["--follow-symlinks"])?;
+    assert!(
+        warned.contains("warning: ") && warned.contains("dangling.txt"),
+        "got: {}",
+        warned
+    );
+
+    fs::set_permissions(base.join("locked"), fs::Permissions::from_mode(0o000))?;
+    // Root reads the directory anyway, so there is no error to report
+    let enforced = fs::read_dir(base.join("locked")).is_err();
+    let warned = stderr(&[]);
+    fs::set_permissions(base.join("locked"), fs::Permissions::from_mode(0o755))?;
+    if enforced {
+        let warned = warned?;
+        assert!(
+            warned.contains("warning: ") && warned.contains("locked"),
+            "got: {}",
+            warned
+        );
+    }
+    Ok(())
+}
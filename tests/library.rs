@@ -0,0 +1,29 @@
+use eyre::Result;
+use kat::Kat;
+use std::fs;
+
+#[test]
+fn matched_files_through_the_library() -> Result<()> {
+    let config_dir = tempfile::tempdir()?;
+    fs::write(
+        config_dir.path().join("rust.yml"),
+        "about: Rust sources\nincluded_paths: [\"Cargo.toml\", \"src/**/*.rs\"]\nexcluded_paths: [\"src/generated/**\"]\n",
+    )?;
+
+    let tree = tempfile::tempdir()?;
+    let base = tree.path().canonicalize()?;
+    fs::create_dir_all(base.join("src/generated"))?;
+    fs::write(base.join("Cargo.toml"), "[package]\n")?;
+    fs::write(base.join("README.md"), "")?;
+    fs::write(base.join("src/main.rs"), "fn main() {}\n")?;
+    fs::write(base.join("src/generated/schema.rs"), "")?;
+
+    let kat = Kat::new(vec![config_dir.path().to_path_buf()])?;
+    assert_eq!(kat.configs["rust"].about, "Rust sources");
+
+    let files = kat.matched_files("rust", Some(base.clone()))?;
+    assert_eq!(files, vec![base.join("Cargo.toml"), base.join("src/main.rs")]);
+
+    assert!(kat.matched_files("python", Some(base)).is_err());
+    Ok(())
+}